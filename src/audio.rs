@@ -0,0 +1,102 @@
+// Purpose: Square-wave beep output driven by the CHIP-8 sound timer.
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const BEEP_FREQUENCY_HZ: f32 = 440.0;
+const BEEP_VOLUME: f32 = 0.15;
+
+// Keeps the cpal stream alive and exposes an atomic on/off switch the
+// emulator thread can flip every time `sound_timer` changes.
+pub struct Beeper {
+    _stream: cpal::Stream,
+    beeping: Arc<AtomicBool>,
+}
+
+impl Beeper {
+    pub fn new() -> Self {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .expect("no audio output device available");
+        let config = device
+            .default_output_config()
+            .expect("no default output config");
+        let sample_rate = config.sample_rate().0 as f32;
+        let channels = config.channels() as usize;
+
+        // one full period of a square wave, read cyclically so samples are
+        // generated on demand inside the callback rather than pushed from
+        // the emulator thread; this keeps the waveform phase-continuous
+        // across beep start/stop and avoids clicking.
+        let period_samples = (sample_rate / BEEP_FREQUENCY_HZ).round() as usize;
+        let ring: Vec<f32> = (0..period_samples.max(1))
+            .map(|i| if i < period_samples / 2 { BEEP_VOLUME } else { -BEEP_VOLUME })
+            .collect();
+
+        let beeping = Arc::new(AtomicBool::new(false));
+        let beeping_cb = Arc::clone(&beeping);
+        let stream_config = config.clone().into();
+
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => {
+                build_stream::<f32>(&device, &stream_config, channels, ring, beeping_cb)
+            }
+            SampleFormat::I16 => {
+                build_stream::<i16>(&device, &stream_config, channels, ring, beeping_cb)
+            }
+            SampleFormat::U16 => {
+                build_stream::<u16>(&device, &stream_config, channels, ring, beeping_cb)
+            }
+            sample_format => panic!("unsupported sample format: {sample_format}"),
+        };
+        stream.play().expect("failed to start cpal output stream");
+
+        Self {
+            _stream: stream,
+            beeping,
+        }
+    }
+
+    pub fn set_beeping(&self, beeping: bool) {
+        self.beeping.store(beeping, Ordering::Relaxed);
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    ring: Vec<f32>,
+    beeping: Arc<AtomicBool>,
+) -> cpal::Stream
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let mut ring_pos = 0usize;
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                let on = beeping.load(Ordering::Relaxed);
+                for frame in data.chunks_mut(channels) {
+                    let sample = if on { ring[ring_pos] } else { 0.0 };
+                    ring_pos = (ring_pos + 1) % ring.len();
+                    let value = T::from_sample(sample);
+                    for out in frame {
+                        *out = value;
+                    }
+                }
+            },
+            |err| println!("cpal output stream error: {}", err),
+            None,
+        )
+        .expect("failed to build cpal output stream")
+}
@@ -0,0 +1,56 @@
+// Purpose: Command-line interface for selecting a ROM and a quirk profile.
+use clap::Parser;
+
+use crate::Quirks;
+
+#[derive(Parser, Debug)]
+#[command(about = "A CHIP-8 emulator")]
+pub(crate) struct Cli {
+    /// Path to the ROM file to load
+    #[arg(default_value = "./ibm.ch8")]
+    pub(crate) rom: String,
+
+    /// Number of CPU cycles to run per 60 Hz frame
+    #[arg(long, default_value_t = 10)]
+    pub(crate) cycles_per_frame: u32,
+
+    /// How much a cleared pixel's brightness is multiplied by each frame (0 = snap off, closer to 1 = slower fade)
+    #[arg(long, default_value_t = 0.6)]
+    pub(crate) fade_factor: f32,
+
+    /// Show the egui debugger overlay (registers, memory, disassembly, step control)
+    #[arg(long)]
+    pub(crate) debug: bool,
+
+    /// 0x8XY6/0x8XYE shift VX in place instead of shifting VY into VX (SUPER-CHIP behavior)
+    #[arg(long)]
+    shift_vx_in_place: bool,
+
+    /// 0xBNNN jumps to NNN + VX instead of NNN + V0 (SUPER-CHIP behavior)
+    #[arg(long)]
+    jump_with_vx: bool,
+
+    /// 0xFX55/0xFX65 leave I unchanged instead of advancing it by X + 1 (SUPER-CHIP behavior)
+    #[arg(long)]
+    no_load_store_increment: bool,
+
+    /// sprites wrap around screen edges instead of clipping (SUPER-CHIP behavior)
+    #[arg(long)]
+    wrap_sprites: bool,
+
+    /// 0x8XY1/0x8XY2/0x8XY3 leave VF untouched instead of resetting it to 0
+    #[arg(long)]
+    no_vf_reset: bool,
+}
+
+impl Cli {
+    pub(crate) fn quirks(&self) -> Quirks {
+        Quirks {
+            shift_vx_in_place: self.shift_vx_in_place,
+            jump_with_vx: self.jump_with_vx,
+            load_store_increments_i: !self.no_load_store_increment,
+            clip_sprites: !self.wrap_sprites,
+            vf_reset: !self.no_vf_reset,
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// Purpose: egui overlay for inspecting emulator state and stepping through execution.
+use crate::{disassemble, State};
+use egui_wgpu::{Renderer, ScreenDescriptor};
+use pixels::{wgpu, PixelsContext};
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+pub(crate) struct Debugger {
+    egui_ctx: egui::Context,
+    egui_state: egui_winit::State,
+    renderer: Renderer,
+    screen_descriptor: ScreenDescriptor,
+    pub(crate) paused: bool,
+    breakpoint: Option<u16>,
+    breakpoint_text: String,
+}
+
+impl Debugger {
+    pub(crate) fn new(
+        window: &Window,
+        device: &wgpu::Device,
+        texture_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+        scale_factor: f32,
+    ) -> Self {
+        let egui_ctx = egui::Context::default();
+        let egui_state = egui_winit::State::new(
+            egui_ctx.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(scale_factor),
+            None,
+            None,
+        );
+        let renderer = Renderer::new(device, texture_format, Default::default());
+
+        Self {
+            egui_ctx,
+            egui_state,
+            renderer,
+            screen_descriptor: ScreenDescriptor {
+                size_in_pixels: [width, height],
+                pixels_per_point: scale_factor,
+            },
+            paused: false,
+            breakpoint: None,
+            breakpoint_text: String::new(),
+        }
+    }
+
+    // returns true if egui claimed the event, so the caller shouldn't also
+    // treat it as hex-keypad input
+    pub(crate) fn handle_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.egui_state.on_window_event(window, event).consumed
+    }
+
+    pub(crate) fn hit_breakpoint(&self, pc: u16) -> bool {
+        self.breakpoint == Some(pc)
+    }
+
+    pub(crate) fn render(
+        &mut self,
+        window: &Window,
+        context: &PixelsContext,
+        encoder: &mut wgpu::CommandEncoder,
+        render_target: &wgpu::TextureView,
+        state: &State,
+        step_requested: &mut bool,
+    ) {
+        let device = &context.device;
+        let queue = &context.queue;
+        let raw_input = self.egui_state.take_egui_input(window);
+        let paused = self.paused;
+        let breakpoint_text = &mut self.breakpoint_text;
+        let breakpoint = &mut self.breakpoint;
+        let paused_toggle = &mut self.paused;
+        let full_output = self.egui_ctx.run_ui(raw_input, |ui| {
+            egui::Window::new("CHIP-8 Debugger").show(ui.ctx(), |ui| {
+                ui.label(format!("pc: 0x{:04X}  i: 0x{:04X}", state.pc, state.i));
+
+                ui.horizontal(|ui| {
+                    if ui.button(if paused { "Resume" } else { "Pause" }).clicked() {
+                        *paused_toggle = !*paused_toggle;
+                    }
+                    if ui.add_enabled(paused, egui::Button::new("Step")).clicked() {
+                        *step_requested = true;
+                    }
+                });
+
+                ui.separator();
+                ui.label("Registers");
+                egui::Grid::new("registers").show(ui, |ui| {
+                    for row in 0..4 {
+                        for col in 0..4 {
+                            let reg = row * 4 + col;
+                            ui.label(format!("V{reg:X}: 0x{:02X}", state.v[reg]));
+                        }
+                        ui.end_row();
+                    }
+                });
+
+                ui.separator();
+                ui.label(format!("Stack: {:04X?}", state.stack));
+
+                ui.separator();
+                ui.label("Breakpoint (hex pc, blank to clear)");
+                if ui.text_edit_singleline(breakpoint_text).changed() {
+                    *breakpoint = u16::from_str_radix(breakpoint_text.trim_start_matches("0x"), 16).ok();
+                }
+
+                ui.separator();
+                ui.label("Disassembly");
+                egui::ScrollArea::vertical()
+                    .id_salt("disassembly")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        let mut pc = state.pc as usize;
+                        for _ in 0..12 {
+                            if pc + 1 >= state.memory.len() {
+                                break;
+                            }
+                            let opcode = (state.memory[pc] as u16) << 8 | state.memory[pc + 1] as u16;
+                            ui.monospace(format!("0x{pc:04X}: {}", disassemble(opcode)));
+                            pc += 2;
+                        }
+                    });
+
+                ui.separator();
+                ui.label("Memory");
+                egui::ScrollArea::vertical()
+                    .id_salt("memory")
+                    .max_height(200.0)
+                    .show(ui, |ui| {
+                        for (row, chunk) in state.memory.chunks(16).enumerate() {
+                            let bytes: Vec<String> = chunk.iter().map(|b| format!("{b:02X}")).collect();
+                            ui.monospace(format!("0x{:04X}: {}", row * 16, bytes.join(" ")));
+                        }
+                    });
+            });
+        });
+
+        self.egui_state
+            .handle_platform_output(window, full_output.platform_output);
+        let clipped_primitives = self
+            .egui_ctx
+            .tessellate(full_output.shapes, full_output.pixels_per_point);
+
+        for (id, image_delta) in &full_output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, image_delta);
+        }
+        self.renderer
+            .update_buffers(device, queue, encoder, &clipped_primitives, &self.screen_descriptor);
+
+        let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("egui-debugger"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        // egui-wgpu's renderer wants a 'static RenderPass so it can be stored
+        // alongside the encoder; this opts out of the borrow-checked
+        // encoder/pass lifetime link since the pass is dropped right below.
+        let mut render_pass = render_pass.forget_lifetime();
+        self.renderer
+            .render(&mut render_pass, &clipped_primitives, &self.screen_descriptor);
+        drop(render_pass);
+
+        for id in &full_output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
@@ -1,117 +1,415 @@
 // Purpose: Main file for the project.
+mod audio;
+mod cli;
+mod debugger;
+
+use audio::Beeper;
+use clap::Parser;
+use cli::Cli;
+use debugger::Debugger;
 use std::io;
 use pixels::{Pixels, SurfaceTexture};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use winit::{
+    application::ApplicationHandler,
     dpi::LogicalSize,
-    event::{ElementState, Event, KeyEvent, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event::{ElementState, KeyEvent, WindowEvent},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     keyboard::{Key, NamedKey},
-    window::WindowBuilder,
+    window::{Window, WindowId},
 };
 
 const WIDTH: u32 = 64;
 const HEIGHT: u32 = 32;
+const DISPLAY_SIZE: usize = (WIDTH * HEIGHT) as usize;
+
+// each hex digit's sprite is 5 bytes tall, loaded at FONT_BASE in memory
+const FONT_BASE: u16 = 0x000;
+const FONT_SET: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// Quirk flags covering ambiguous opcodes that differ between the classic
+// COSMAC VIP interpreter and later SUPER-CHIP interpreters. Defaults match
+// the COSMAC VIP; CLI flags let a ROM opt into the SUPER-CHIP behavior.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Quirks {
+    pub(crate) shift_vx_in_place: bool,
+    pub(crate) jump_with_vx: bool,
+    pub(crate) load_store_increments_i: bool,
+    pub(crate) clip_sprites: bool,
+    pub(crate) vf_reset: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            shift_vx_in_place: false,
+            jump_with_vx: false,
+            load_store_increments_i: true,
+            clip_sprites: true,
+            vf_reset: true,
+        }
+    }
+}
 
-struct State {
-    memory: [u8; 4096],
-    pc: u16,
-    // stack: Vec<u16>,
-    i: u16,
-    // delay_timer: u8,
-    // sound_timer: u8,
-    v: [u8; 16],
+pub(crate) struct State {
+    pub(crate) memory: [u8; 4096],
+    pub(crate) pc: u16,
+    pub(crate) stack: Vec<u16>,
+    pub(crate) i: u16,
+    delay_timer: u8,
+    sound_timer: u8,
+    pub(crate) v: [u8; 16],
+    keys: [bool; 16],
+    // keys as of the end of the previous cycle, used to detect a fresh
+    // key-press edge for FX0A rather than "any key currently held"
+    keys_prev: [bool; 16],
+    quirks: Quirks,
+    // logical on/off screen state used for the XOR/collision rules
+    display: [bool; DISPLAY_SIZE],
+    // per-pixel brightness used to fade cleared pixels out instead of
+    // snapping them straight to black
+    intensity: [u8; DISPLAY_SIZE],
 }
 
 impl State {
-    fn new() -> Self {
+    fn new(quirks: Quirks) -> Self {
+        let mut memory = [0; 4096];
+        memory[FONT_BASE as usize..FONT_BASE as usize + FONT_SET.len()].copy_from_slice(&FONT_SET);
+
         Self {
-            memory: [0; 4096],
-            pc: 0x0000,
-            // stack: Vec::new(),
+            memory,
+            // ROMs are loaded at 0x200 by read_program_into_memory
+            pc: 0x200,
+            stack: Vec::new(),
             i: 0x0000,
-            // delay_timer: 0x00,
-            // sound_timer: 0x00,
+            delay_timer: 0x00,
+            sound_timer: 0x00,
             v: [0; 16],
+            keys: [false; 16],
+            keys_prev: [false; 16],
+            quirks,
+            display: [false; DISPLAY_SIZE],
+            intensity: [0; DISPLAY_SIZE],
         }
     }
 }
 
-fn clear_screen(pixels: &mut Pixels) {
-    let frame = pixels.frame_mut();
-    for pixel in frame.chunks_exact_mut(4) {
-        // RGBA to black
-        pixel.copy_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+// maps the physical 4x4 key block (1234 / QWER / ASDF / ZXCV) to the
+// CHIP-8 hex keypad (123C / 456D / 789E / A0BF layout)
+fn hex_key_index(key: &Key) -> Option<usize> {
+    match key {
+        Key::Character(s) => match s.as_str() {
+            "1" => Some(0x1),
+            "2" => Some(0x2),
+            "3" => Some(0x3),
+            "4" => Some(0xC),
+            "q" | "Q" => Some(0x4),
+            "w" | "W" => Some(0x5),
+            "e" | "E" => Some(0x6),
+            "r" | "R" => Some(0xD),
+            "a" | "A" => Some(0x7),
+            "s" | "S" => Some(0x8),
+            "d" | "D" => Some(0x9),
+            "f" | "F" => Some(0xE),
+            "z" | "Z" => Some(0xA),
+            "x" | "X" => Some(0x0),
+            "c" | "C" => Some(0xB),
+            "v" | "V" => Some(0xF),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
-fn jump_to(state: &mut State, opcode: u16) {
-    // opcode is 0x1NNN, mask with 0x0FFF to get NNN
-    state.pc = opcode & 0x0FFF;
+fn clear_screen(state: &mut State) {
+    state.display = [false; DISPLAY_SIZE];
+    state.intensity = [0; DISPLAY_SIZE];
 }
 
-fn set_vx(state: &mut State, opcode: u16) {
-    // mask second nibble with 0x0F00 and shift right 8 bits to get x
-    let x = opcode & 0x0F00 >> 8;
-    // mask last byte with 0x00FF to get value
-    let value = (opcode & 0x00FF) as u8;
-    state.v[x as usize] = value;
+fn jump_to(state: &mut State, nnn: u16) {
+    state.pc = nnn;
 }
 
-fn add_vx(state: &mut State, opcode: u16) {
-    // mask second nibble with 0x0F00 and shift right 8 bits to get x
-    let x = (opcode & 0x0F00) >> 8;
-    // mask last byte with 0x00FF to get value
-    let value = (opcode & 0x00FF) as u8;
-    state.v[x as usize] += value;
+fn call(state: &mut State, nnn: u16) {
+    // push the return address, then jump into the subroutine
+    state.stack.push(state.pc);
+    state.pc = nnn;
 }
 
-fn set_i(state: &mut State, opcode: u16) {
-    let value = opcode & 0x0FFF;
-    state.i = value;
+fn ret(state: &mut State) {
+    // pop the return address pushed by CALL back into pc; a malformed ROM
+    // can execute RET with no matching CALL, so ignore rather than panic
+    match state.stack.pop() {
+        Some(addr) => state.pc = addr,
+        None => println!("Ignoring RET with empty call stack at 0x{:04X}", state.pc.wrapping_sub(2)),
+    }
 }
 
-fn display(state: &mut State, pixels: &mut Pixels, opcode: u16) {
-    let frame = pixels.frame_mut();
-    let x = (state.v[((opcode & 0x0F00) >> 8) as usize] % WIDTH as u8) as usize;
-    let y = (state.v[((opcode & 0x00F0) >> 4) as usize] % HEIGHT as u8) as usize;
-    let rows = opcode & 0x000F;
-    let stride = WIDTH as usize * 4;
+fn skip_if_vx_eq_kk(state: &mut State, x: usize, kk: u8) {
+    if state.v[x] == kk {
+        state.pc += 2;
+    }
+}
+
+fn skip_if_vx_neq_kk(state: &mut State, x: usize, kk: u8) {
+    if state.v[x] != kk {
+        state.pc += 2;
+    }
+}
+
+fn skip_if_vx_eq_vy(state: &mut State, x: usize, y: usize) {
+    if state.v[x] == state.v[y] {
+        state.pc += 2;
+    }
+}
+
+fn skip_if_vx_neq_vy(state: &mut State, x: usize, y: usize) {
+    if state.v[x] != state.v[y] {
+        state.pc += 2;
+    }
+}
+
+fn set_vx(state: &mut State, x: usize, kk: u8) {
+    state.v[x] = kk;
+}
+
+fn add_vx(state: &mut State, x: usize, kk: u8) {
+    state.v[x] = state.v[x].wrapping_add(kk);
+}
+
+fn ld_vx_vy(state: &mut State, x: usize, y: usize) {
+    state.v[x] = state.v[y];
+}
+
+fn or_vx_vy(state: &mut State, x: usize, y: usize) {
+    state.v[x] |= state.v[y];
+    if state.quirks.vf_reset {
+        state.v[0xF] = 0;
+    }
+}
+
+fn and_vx_vy(state: &mut State, x: usize, y: usize) {
+    state.v[x] &= state.v[y];
+    if state.quirks.vf_reset {
+        state.v[0xF] = 0;
+    }
+}
+
+fn xor_vx_vy(state: &mut State, x: usize, y: usize) {
+    state.v[x] ^= state.v[y];
+    if state.quirks.vf_reset {
+        state.v[0xF] = 0;
+    }
+}
+
+fn add_vx_vy(state: &mut State, x: usize, y: usize) {
+    let (result, carry) = state.v[x].overflowing_add(state.v[y]);
+    state.v[x] = result;
+    state.v[0xF] = carry as u8;
+}
+
+fn sub_vx_vy(state: &mut State, x: usize, y: usize) {
+    let (result, borrow) = state.v[x].overflowing_sub(state.v[y]);
+    state.v[x] = result;
+    state.v[0xF] = !borrow as u8;
+}
+
+fn subn_vx_vy(state: &mut State, x: usize, y: usize) {
+    let (result, borrow) = state.v[y].overflowing_sub(state.v[x]);
+    state.v[x] = result;
+    state.v[0xF] = !borrow as u8;
+}
+
+fn shr_vx(state: &mut State, x: usize, y: usize) {
+    let source = if state.quirks.shift_vx_in_place {
+        state.v[x]
+    } else {
+        state.v[y]
+    };
+    state.v[x] = source >> 1;
+    state.v[0xF] = source & 0x1;
+}
+
+fn shl_vx(state: &mut State, x: usize, y: usize) {
+    let source = if state.quirks.shift_vx_in_place {
+        state.v[x]
+    } else {
+        state.v[y]
+    };
+    state.v[x] = source << 1;
+    state.v[0xF] = (source & 0x80) >> 7;
+}
+
+fn set_i(state: &mut State, nnn: u16) {
+    state.i = nnn;
+}
+
+fn jump_with_offset(state: &mut State, nnn: u16, x: usize) {
+    let offset = if state.quirks.jump_with_vx {
+        state.v[x]
+    } else {
+        state.v[0]
+    };
+    state.pc = nnn + offset as u16;
+}
+
+fn ld_vx_dt(state: &mut State, x: usize) {
+    state.v[x] = state.delay_timer;
+}
+
+fn ld_dt_vx(state: &mut State, x: usize) {
+    state.delay_timer = state.v[x];
+}
+
+fn ld_st_vx(state: &mut State, x: usize) {
+    state.sound_timer = state.v[x];
+}
+
+fn skip_if_key_down(state: &mut State, x: usize) {
+    // VX can hold any byte; mask to the 16-entry keypad range so a
+    // garbage/oversized value can't index out of bounds
+    if state.keys[(state.v[x] & 0x0F) as usize] {
+        state.pc += 2;
+    }
+}
+
+fn skip_if_key_up(state: &mut State, x: usize) {
+    if !state.keys[(state.v[x] & 0x0F) as usize] {
+        state.pc += 2;
+    }
+}
+
+fn wait_for_key(state: &mut State, x: usize) {
+    // only a freshly-pressed key (down now, up last cycle) satisfies the
+    // wait, so a key already held when FX0A starts does not complete it
+    match (0..16).find(|&i| state.keys[i] && !state.keys_prev[i]) {
+        Some(index) => state.v[x] = index as u8,
+        // no new press yet: rewind pc so this opcode is fetched again
+        // next cycle, blocking execution until a key is pressed
+        None => state.pc -= 2,
+    }
+}
+
+fn rand_vx(state: &mut State, x: usize, kk: u8) {
+    state.v[x] = rand::random::<u8>() & kk;
+}
+
+fn add_i_vx(state: &mut State, x: usize) {
+    state.i += state.v[x] as u16;
+}
+
+fn ld_i_font(state: &mut State, x: usize) {
+    state.i = FONT_BASE + state.v[x] as u16 * 5;
+}
+
+fn store_bcd(state: &mut State, x: usize) {
+    let value = state.v[x];
+    let i = state.i as usize;
+    state.memory[i] = value / 100;
+    state.memory[i + 1] = (value / 10) % 10;
+    state.memory[i + 2] = value % 10;
+}
+
+fn store_registers(state: &mut State, x: usize) {
+    let i = state.i as usize;
+    for offset in 0..=x {
+        state.memory[i + offset] = state.v[offset];
+    }
+    if state.quirks.load_store_increments_i {
+        state.i += x as u16 + 1;
+    }
+}
+
+fn load_registers(state: &mut State, x: usize) {
+    let i = state.i as usize;
+    for offset in 0..=x {
+        state.v[offset] = state.memory[i + offset];
+    }
+    if state.quirks.load_store_increments_i {
+        state.i += x as u16 + 1;
+    }
+}
+
+// decrements both timers once per 60 Hz tick, independent of how many
+// CPU instructions ran that tick
+fn tick_timers(state: &mut State) {
+    if state.delay_timer > 0 {
+        state.delay_timer -= 1;
+    }
+    if state.sound_timer > 0 {
+        state.sound_timer -= 1;
+    }
+}
+
+fn display(state: &mut State, x: usize, y: usize, n: u16) {
+    let x = (state.v[x] % WIDTH as u8) as usize;
+    let y = (state.v[y] % HEIGHT as u8) as usize;
+    let rows = n;
+    let clip = state.quirks.clip_sprites;
 
     state.v[0xF] = 0;
 
     for row in 0..rows {
-        let sprite_y = y + row as usize;
+        let raw_y = y + row as usize;
+        if clip && raw_y >= HEIGHT as usize {
+            continue;
+        }
+        let sprite_y = raw_y % HEIGHT as usize;
         let sprite_row = state.memory[(state.i + row) as usize];
         for col in 0..8 {
-            let sprite_x = x + col;
-            // let sprite_pixel = (sprite_row >> col) & 1;
             let sprite_pixel = (sprite_row >> (7 - col)) & 1;
             if sprite_pixel == 0 {
                 continue;
             }
-            let pixel_index = (sprite_y * stride) + (sprite_x * 4);
-            let pixel = &mut frame[pixel_index..pixel_index + 4];
-            // each pixel in here is RGBA
-            if pixel == [0, 0, 0, 0] {
-                pixel[0] = 0xFF; // R
-                pixel[1] = 0xFF; // G
-                pixel[2] = 0xFF; // B
-                pixel[3] = 0xFF; // A
+            let raw_x = x + col;
+            if clip && raw_x >= WIDTH as usize {
+                continue;
+            }
+            let sprite_x = raw_x % WIDTH as usize;
+            let index = sprite_y * WIDTH as usize + sprite_x;
+
+            let was_on = state.display[index];
+            state.display[index] ^= true;
+            if was_on {
                 state.v[0xF] = 1;
             } else {
-                pixel[0] = 0x00; // R
-                pixel[1] = 0x00; // G
-                pixel[2] = 0x00; // B
-                pixel[3] = 0xFF; // A
-            }
-            if sprite_x == WIDTH as usize {
-                continue;
+                // pixel just turned on: peg it to full brightness
+                state.intensity[index] = 0xFF;
             }
         }
-        if sprite_y == HEIGHT as usize {
-            continue;
+    }
+}
+
+// decays cleared pixels toward black instead of snapping them off, then
+// writes the resulting grey level into the pixels frame; called once per
+// 60 Hz tick, independent of how many opcodes ran that tick
+fn present(state: &mut State, pixels: &mut Pixels, fade_factor: f32) {
+    let frame = pixels.frame_mut();
+    for index in 0..DISPLAY_SIZE {
+        if !state.display[index] {
+            state.intensity[index] = (state.intensity[index] as f32 * fade_factor) as u8;
         }
+        let level = state.intensity[index];
+        let pixel_index = index * 4;
+        frame[pixel_index..pixel_index + 4].copy_from_slice(&[level, level, level, 0xFF]);
     }
 }
 
@@ -122,120 +420,319 @@ fn fetch_opcode(state: &mut State) -> u16 {
     opcode
 }
 
-fn execute_opcode(opcode: u16, state: &mut State, pixels: &mut Pixels) {
-    // match opcode category
-    match opcode & 0xF000 {
-        // 0 category
-        0x0000 => match opcode & 0x000F {
-            // 0 category with last nibble to 0 is clear screen
-            0x0000 => clear_screen(pixels),
-            _ => unimplemented!(),
-        },
-        // 1 category is jump to address
-        0x1000 => jump_to(state, opcode),
-        // 6 category is set vx to nn
-        0x6000 => set_vx(state, opcode),
-        // 7 category is add vx to nn
-        0x7000 => add_vx(state, opcode),
-        // A category is set i to nnn
-        0xA000 => set_i(state, opcode),
-        // D category is draw
-        0xD000 => display(state, pixels, opcode),
-        _ => unimplemented!(),
+fn execute_opcode(opcode: u16, state: &mut State) {
+    // split the opcode into its four nibbles plus the common operand shapes
+    let nib1 = (opcode & 0xF000) >> 12;
+    let nib2 = (opcode & 0x0F00) >> 8;
+    let nib3 = (opcode & 0x00F0) >> 4;
+    let nib4 = opcode & 0x000F;
+    let nnn = opcode & 0x0FFF;
+    let kk = (opcode & 0x00FF) as u8;
+    let x = nib2 as usize;
+    let y = nib3 as usize;
+
+    match (nib1, nib2, nib3, nib4) {
+        (0x0, 0x0, 0xE, 0x0) => clear_screen(state),
+        (0x0, 0x0, 0xE, 0xE) => ret(state),
+        (0x1, _, _, _) => jump_to(state, nnn),
+        (0x2, _, _, _) => call(state, nnn),
+        (0x3, _, _, _) => skip_if_vx_eq_kk(state, x, kk),
+        (0x4, _, _, _) => skip_if_vx_neq_kk(state, x, kk),
+        (0x5, _, _, 0x0) => skip_if_vx_eq_vy(state, x, y),
+        (0x6, _, _, _) => set_vx(state, x, kk),
+        (0x7, _, _, _) => add_vx(state, x, kk),
+        (0x8, _, _, 0x0) => ld_vx_vy(state, x, y),
+        (0x8, _, _, 0x1) => or_vx_vy(state, x, y),
+        (0x8, _, _, 0x2) => and_vx_vy(state, x, y),
+        (0x8, _, _, 0x3) => xor_vx_vy(state, x, y),
+        (0x8, _, _, 0x4) => add_vx_vy(state, x, y),
+        (0x8, _, _, 0x5) => sub_vx_vy(state, x, y),
+        (0x8, _, _, 0x6) => shr_vx(state, x, y),
+        (0x8, _, _, 0x7) => subn_vx_vy(state, x, y),
+        (0x8, _, _, 0xE) => shl_vx(state, x, y),
+        (0x9, _, _, 0x0) => skip_if_vx_neq_vy(state, x, y),
+        (0xA, _, _, _) => set_i(state, nnn),
+        (0xB, _, _, _) => jump_with_offset(state, nnn, x),
+        (0xC, _, _, _) => rand_vx(state, x, kk),
+        (0xD, _, _, _) => display(state, x, y, nib4),
+        (0xE, _, 0x9, 0xE) => skip_if_key_down(state, x),
+        (0xE, _, 0xA, 0x1) => skip_if_key_up(state, x),
+        (0xF, _, 0x0, 0x7) => ld_vx_dt(state, x),
+        (0xF, _, 0x0, 0xA) => wait_for_key(state, x),
+        (0xF, _, 0x1, 0x5) => ld_dt_vx(state, x),
+        (0xF, _, 0x1, 0x8) => ld_st_vx(state, x),
+        (0xF, _, 0x1, 0xE) => add_i_vx(state, x),
+        (0xF, _, 0x2, 0x9) => ld_i_font(state, x),
+        (0xF, _, 0x3, 0x3) => store_bcd(state, x),
+        (0xF, _, 0x5, 0x5) => store_registers(state, x),
+        (0xF, _, 0x6, 0x5) => load_registers(state, x),
+        // unrecognized opcode (e.g. 0x0NNN SYS, or non-opcode data
+        // executed as code): log and skip rather than aborting the run
+        _ => println!("Ignoring unknown opcode 0x{opcode:04X} at 0x{:04X}", state.pc.wrapping_sub(2)),
+    }
+}
+
+fn step(state: &mut State) {
+    let opcode = fetch_opcode(state);
+    execute_opcode(opcode, state);
+    state.keys_prev = state.keys;
+}
+
+// decodes an opcode into a mnemonic using the same nibble/operand shapes
+// as `execute_opcode`, for the debugger's disassembly view
+pub(crate) fn disassemble(opcode: u16) -> String {
+    let nib1 = (opcode & 0xF000) >> 12;
+    let nib2 = (opcode & 0x0F00) >> 8;
+    let nib3 = (opcode & 0x00F0) >> 4;
+    let nib4 = opcode & 0x000F;
+    let nnn = opcode & 0x0FFF;
+    let kk = opcode & 0x00FF;
+    let x = nib2;
+    let y = nib3;
+
+    match (nib1, nib2, nib3, nib4) {
+        (0x0, 0x0, 0xE, 0x0) => "CLS".to_string(),
+        (0x0, 0x0, 0xE, 0xE) => "RET".to_string(),
+        (0x1, _, _, _) => format!("JP 0x{nnn:03X}"),
+        (0x2, _, _, _) => format!("CALL 0x{nnn:03X}"),
+        (0x3, _, _, _) => format!("SE V{x:X}, 0x{kk:02X}"),
+        (0x4, _, _, _) => format!("SNE V{x:X}, 0x{kk:02X}"),
+        (0x5, _, _, 0x0) => format!("SE V{x:X}, V{y:X}"),
+        (0x6, _, _, _) => format!("LD V{x:X}, 0x{kk:02X}"),
+        (0x7, _, _, _) => format!("ADD V{x:X}, 0x{kk:02X}"),
+        (0x8, _, _, 0x0) => format!("LD V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x1) => format!("OR V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x2) => format!("AND V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x3) => format!("XOR V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x4) => format!("ADD V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x5) => format!("SUB V{x:X}, V{y:X}"),
+        (0x8, _, _, 0x6) => format!("SHR V{x:X} {{, V{y:X}}}"),
+        (0x8, _, _, 0x7) => format!("SUBN V{x:X}, V{y:X}"),
+        (0x8, _, _, 0xE) => format!("SHL V{x:X} {{, V{y:X}}}"),
+        (0x9, _, _, 0x0) => format!("SNE V{x:X}, V{y:X}"),
+        (0xA, _, _, _) => format!("LD I, 0x{nnn:03X}"),
+        (0xB, _, _, _) => format!("JP V0, 0x{nnn:03X}"),
+        (0xC, _, _, _) => format!("RND V{x:X}, 0x{kk:02X}"),
+        (0xD, _, _, _) => format!("DRW V{x:X}, V{y:X}, 0x{nib4:X}"),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{x:X}"),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{x:X}"),
+        (0xF, _, 0x0, 0x7) => format!("LD V{x:X}, DT"),
+        (0xF, _, 0x0, 0xA) => format!("LD V{x:X}, K"),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{x:X}"),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{x:X}"),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{x:X}"),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{x:X}"),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{x:X}"),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{x:X}"),
+        (0xF, _, 0x6, 0x5) => format!("LD V{x:X}, [I]"),
+        _ => format!("DW 0x{opcode:04X}"),
     }
 }
 
 fn read_program_into_memory(filepath: &str, state: &mut State) -> io::Result<()> {
     let program = std::fs::read(filepath)?;
-    let mut pc = 0x200;
-    for byte in program {
+    for (pc, byte) in (0x200..).zip(program) {
         state.memory[pc] = byte;
-        pc += 1;
     }
 
     Ok(())
 }
 
-fn run(mut state: State) {
-    env_logger::init();
-    let event_loop = EventLoop::new().unwrap();
-    let window = {
+// Holds everything that can only be created once a `Window` exists (winit
+// 0.30 requires windows to be created from inside `resumed`, so these start
+// out `None` and are populated on the first `resumed` call).
+struct App {
+    state: State,
+    cycles_per_frame: u32,
+    fade_factor: f32,
+    debug: bool,
+    target_frame_time: Duration,
+    window: Option<Arc<Window>>,
+    pixels: Option<Pixels<'static>>,
+    beeper: Option<Beeper>,
+    debugger: Option<Debugger>,
+}
+
+impl App {
+    fn new(state: State, cycles_per_frame: u32, fade_factor: f32, debug: bool) -> Self {
+        Self {
+            state,
+            cycles_per_frame,
+            fade_factor,
+            debug,
+            target_frame_time: Duration::from_secs_f64(1.0 / 60.0),
+            window: None,
+            pixels: None,
+            beeper: None,
+            debugger: None,
+        }
+    }
+
+    fn redraw(&mut self, event_loop: &ActiveEventLoop) {
+        let window: &Window = self.window.as_ref().unwrap().as_ref();
+        let pixels = self.pixels.as_mut().unwrap();
+
+        // run several CPU cycles per 60 Hz tick so emulation speed isn't
+        // capped at the display refresh rate; the render below still only
+        // happens once per tick
+        let paused = self.debugger.as_ref().is_some_and(|dbg| dbg.paused);
+        if !paused {
+            for _ in 0..self.cycles_per_frame {
+                step(&mut self.state);
+                if let Some(dbg) = self.debugger.as_mut() {
+                    if dbg.hit_breakpoint(self.state.pc) {
+                        dbg.paused = true;
+                        break;
+                    }
+                }
+            }
+        }
+        tick_timers(&mut self.state);
+        if let Some(beeper) = &self.beeper {
+            beeper.set_beeping(self.state.sound_timer > 0);
+        }
+        present(&mut self.state, pixels, self.fade_factor);
+
+        let mut step_requested = false;
+        let state = &self.state;
+        let debugger = &mut self.debugger;
+        let render_result = pixels.render_with(|encoder, render_target, context| {
+            context.scaling_renderer.render(encoder, render_target);
+            if let Some(dbg) = debugger.as_mut() {
+                dbg.render(
+                    window,
+                    context,
+                    encoder,
+                    render_target,
+                    state,
+                    &mut step_requested,
+                );
+            }
+            Ok(())
+        });
+
+        if step_requested {
+            step(&mut self.state);
+        }
+
+        if render_result
+            .map_err(|e| println!("pixels.render() failed: {}", e))
+            .is_err()
+        {
+            event_loop.exit();
+        }
+    }
+}
+
+impl ApplicationHandler for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
         let size = LogicalSize::new(WIDTH, HEIGHT);
-        WindowBuilder::new()
+        let attributes = Window::default_attributes()
             .with_title("Chip-8 Emulator")
             .with_inner_size(size)
-            .with_min_inner_size(size)
-            .build(&event_loop)
-            .unwrap()
-    };
-    let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WIDTH, HEIGHT, surface_texture).unwrap()
-    };
-    let target_frame_time = Duration::from_secs_f64(1.0 / 60.0);
+            .with_min_inner_size(size);
+        // wrapped in an Arc so the Pixels surface can own a handle to the
+        // window (satisfying wgpu::WindowHandle) without borrowing from self
+        let window = Arc::new(event_loop.create_window(attributes).unwrap());
 
-    event_loop.set_control_flow(ControlFlow::Poll);
-    let _ = event_loop.run(move |event, elwt| {
+        let pixels = {
+            let window_size = window.inner_size();
+            let surface_texture =
+                SurfaceTexture::new(window_size.width, window_size.height, Arc::clone(&window));
+            Pixels::new(WIDTH, HEIGHT, surface_texture).unwrap()
+        };
+
+        self.beeper = Some(Beeper::new());
+        self.debugger = self.debug.then(|| {
+            let window_size = window.inner_size();
+            Debugger::new(
+                window.as_ref(),
+                pixels.device(),
+                pixels.render_texture_format(),
+                window_size.width,
+                window_size.height,
+                window.scale_factor() as f32,
+            )
+        });
+
+        event_loop.set_control_flow(ControlFlow::Poll);
+        self.pixels = Some(pixels);
+        self.window = Some(window);
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _window_id: WindowId, event: WindowEvent) {
         let frame_start = Instant::now();
 
+        let window: &Window = self.window.as_ref().unwrap().as_ref();
+        let consumed_by_debugger = self
+            .debugger
+            .as_mut()
+            .is_some_and(|dbg| dbg.handle_event(window, &event));
+
         match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::RedrawRequested => {
-                    let opcode = fetch_opcode(&mut state);
-                    execute_opcode(opcode, &mut state, &mut pixels);
-
-                    if pixels
-                        .render()
-                        .map_err(|e| println!("pixels.render() failed: {}", e))
-                        .is_err()
-                    {
-                        elwt.exit();
-                        return;
-                    }
-                }
-                WindowEvent::CloseRequested => {
-                    println!("Closing the window");
-                    elwt.exit();
-                    return;
-                }
-                WindowEvent::KeyboardInput {
-                    event:
-                        KeyEvent {
-                            logical_key: Key::Named(NamedKey::Escape),
-                            state: ElementState::Released,
-                            ..
-                        },
-                    ..
-                } => {
-                    println!("Closing the window");
-                    elwt.exit();
-                    return;
+            WindowEvent::RedrawRequested => self.redraw(event_loop),
+            WindowEvent::CloseRequested => {
+                println!("Closing the window");
+                event_loop.exit();
+                return;
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        logical_key: Key::Named(NamedKey::Escape),
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            } => {
+                println!("Closing the window");
+                event_loop.exit();
+                return;
+            }
+            WindowEvent::KeyboardInput { event, .. } if !consumed_by_debugger => {
+                if let Some(index) = hex_key_index(&event.logical_key) {
+                    self.state.keys[index] = event.state == ElementState::Pressed;
                 }
-                _ => (),
-            },
-            Event::AboutToWait => {
-                window.request_redraw();
             }
             _ => (),
         }
 
         let frame_duration = frame_start.elapsed();
-
-        if frame_duration < target_frame_time {
+        if frame_duration < self.target_frame_time {
             // If the frame finished early, wait the remaining time
-            elwt.set_control_flow(ControlFlow::WaitUntil(frame_start + target_frame_time));
+            event_loop.set_control_flow(ControlFlow::WaitUntil(frame_start + self.target_frame_time));
         } else {
             // If the frame took too long, continue immediately
-            elwt.set_control_flow(ControlFlow::Poll);
+            event_loop.set_control_flow(ControlFlow::Poll);
         }
-    });
+    }
+
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+}
+
+fn run(state: State, cycles_per_frame: u32, fade_factor: f32, debug: bool) {
+    env_logger::init();
+    let event_loop = EventLoop::new().unwrap();
+    let mut app = App::new(state, cycles_per_frame, fade_factor, debug);
+    let _ = event_loop.run_app(&mut app);
 }
 
 fn main() {
-    let mut state = State::new();
-    match read_program_into_memory("./ibm.ch8", &mut state) {
-        Ok(()) => run(state),
+    let cli = Cli::parse();
+    let mut state = State::new(cli.quirks());
+    let cycles_per_frame = cli.cycles_per_frame;
+    let fade_factor = cli.fade_factor;
+    let debug = cli.debug;
+    match read_program_into_memory(&cli.rom, &mut state) {
+        Ok(()) => run(state, cycles_per_frame, fade_factor, debug),
         Err(e) => println!("Failed to read file: {}", e)
     }
 }